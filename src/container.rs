@@ -1,12 +1,61 @@
 use log::{debug, error, info, warn};
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
-use shiplift::{rep::ContainerDetails, ContainerOptions, Docker, PullOptions, RmContainerOptions};
-use std::collections::HashMap;
+use shiplift::{
+    rep::ContainerDetails, tty::TtyChunk, BuildOptions, ContainerOptions, Docker,
+    ExecContainerOptions, LogsOptions, NetworkCreateOptions, PullOptions, RmContainerOptions,
+};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::prelude::{future, Future, Stream};
+use std::time::{Duration, Instant};
+use tar::{Archive, Builder as TarBuilder};
+use tempfile::TempDir;
+use tokio::prelude::{future, Async, Future, Poll, Stream};
+use tokio::timer::Delay;
+use tokio_threadpool::blocking;
+use walkdir::WalkDir;
 
 pub type Client = Arc<Docker>;
 
+/// Errors that can occur while building or interacting with a [`Container`].
+#[derive(Debug)]
+pub enum Error {
+    /// The Docker daemon returned an error.
+    Docker(shiplift::Error),
+    /// The container did not become ready within the configured timeout.
+    Timeout,
+    /// Reading, packaging or unpacking local files failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Docker(e) => write!(f, "docker error: {}", e),
+            Error::Timeout => write!(f, "timed out waiting for container to become ready"),
+            Error::Io(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<shiplift::Error> for Error {
+    fn from(error: shiplift::Error) -> Self {
+        Error::Docker(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
 pub enum Protocol {
     Tcp,
     Udp,
@@ -37,7 +86,7 @@ impl Container {
     pub fn new(
         client: &Client,
         image_name: impl Into<String>,
-    ) -> impl Future<Item = Self, Error = shiplift::Error> {
+    ) -> impl Future<Item = Self, Error = Error> {
         ContainerBuilder::new(client, image_name).build()
     }
 
@@ -53,12 +102,294 @@ impl Container {
         &self.details.network_settings.ports
     }
 
-    pub fn delete(self) -> impl Future<Item = (), Error = shiplift::Error> {
+    pub fn delete(self) -> impl Future<Item = (), Error = Error> {
         self.client
             .containers()
             .get(&self.id())
             .remove(RmContainerOptions::builder().force(true).build())
+            .from_err()
+    }
+
+    /// Run a command inside the (already-running) container and collect its
+    /// combined stdout/stderr.
+    pub fn exec<S: AsRef<str>>(
+        &self,
+        cmd: &[S],
+        options: ExecOptions,
+    ) -> impl Future<Item = ExecOutput, Error = Error> {
+        exec_container(Arc::clone(&self.client), self.id().to_string(), cmd, options)
+    }
+
+    /// Stream the container's stdout/stderr as complete, UTF-8 lines.
+    pub fn logs(&self, options: LogOptions) -> impl Stream<Item = LogLine, Error = Error> {
+        logs_container(Arc::clone(&self.client), self.id().to_string(), options)
+    }
+
+    /// Copy a local file or directory tree into the container at
+    /// `container_path`.
+    pub fn copy_into(
+        &self,
+        local_path: impl AsRef<Path>,
+        container_path: impl Into<String>,
+    ) -> impl Future<Item = (), Error = Error> {
+        copy_into_container(
+            Arc::clone(&self.client),
+            self.id().to_string(),
+            local_path.as_ref().to_path_buf(),
+            container_path.into(),
+        )
+    }
+
+    /// Fetch `container_path` as a raw tar archive.
+    pub fn copy_from(
+        &self,
+        container_path: impl Into<String>,
+    ) -> impl Future<Item = Vec<u8>, Error = Error> {
+        copy_from_container(
+            Arc::clone(&self.client),
+            self.id().to_string(),
+            container_path.into(),
+        )
+    }
+
+    /// Fetch `container_path` and unpack it into the local `destination`
+    /// directory.
+    pub fn copy_from_into(
+        &self,
+        container_path: impl Into<String>,
+        destination: impl AsRef<Path>,
+    ) -> impl Future<Item = (), Error = Error> {
+        let destination = destination.as_ref().to_path_buf();
+
+        self.copy_from(container_path)
+            .and_then(move |archive| unpack_tar(&archive, &destination).map_err(Error::from))
+    }
+}
+
+/// Options controlling [`Container::logs`].
+pub struct LogOptions {
+    pub follow: bool,
+    pub timestamps: bool,
+    pub tail: Option<usize>,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        LogOptions {
+            follow: false,
+            timestamps: false,
+            tail: None,
+        }
+    }
+}
+
+/// Which of the container's output streams a [`LogLine`] came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// A single complete line of container output.
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub stream: LogStream,
+    pub message: String,
+}
+
+fn logs_container(
+    client: Client,
+    id: String,
+    options: LogOptions,
+) -> impl Stream<Item = LogLine, Error = Error> {
+    let mut builder = LogsOptions::builder();
+    builder
+        .stdout(true)
+        .stderr(true)
+        .follow(options.follow)
+        .timestamps(options.timestamps);
+
+    if let Some(tail) = options.tail {
+        builder.tail(&tail.to_string());
     }
+
+    let raw = client.containers().get(&id).logs(&builder.build());
+
+    LineSplitter::new(raw)
+}
+
+/// Demuxes a [`TtyChunk`] stream into complete lines, buffering partial
+/// lines (per stream, so an in-flight stdout line can't be spliced with an
+/// interleaved stderr chunk) across frame boundaries and flushing whatever
+/// remains once the underlying stream ends.
+struct LineSplitter<S> {
+    inner: S,
+    stdout_buf: Vec<u8>,
+    stderr_buf: Vec<u8>,
+    pending: VecDeque<LogLine>,
+    done: bool,
+}
+
+impl<S> LineSplitter<S> {
+    fn new(inner: S) -> Self {
+        LineSplitter {
+            inner,
+            stdout_buf: Vec::new(),
+            stderr_buf: Vec::new(),
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn flush_remainder(&mut self) {
+        if !self.stdout_buf.is_empty() {
+            let message = String::from_utf8_lossy(&self.stdout_buf).into_owned();
+            self.stdout_buf.clear();
+            self.pending.push_back(LogLine {
+                stream: LogStream::Stdout,
+                message,
+            });
+        }
+        if !self.stderr_buf.is_empty() {
+            let message = String::from_utf8_lossy(&self.stderr_buf).into_owned();
+            self.stderr_buf.clear();
+            self.pending.push_back(LogLine {
+                stream: LogStream::Stderr,
+                message,
+            });
+        }
+    }
+}
+
+impl<S> Stream for LineSplitter<S>
+where
+    S: Stream<Item = TtyChunk, Error = shiplift::Error>,
+{
+    type Item = LogLine;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<LogLine>, Error> {
+        loop {
+            if let Some(line) = self.pending.pop_front() {
+                return Ok(Async::Ready(Some(line)));
+            }
+
+            if self.done {
+                return Ok(Async::Ready(None));
+            }
+
+            match self.inner.poll().map_err(Error::from)? {
+                Async::Ready(Some(TtyChunk::StdOut(bytes))) => {
+                    self.stdout_buf.extend(bytes);
+                    split_lines(&mut self.stdout_buf, LogStream::Stdout, &mut self.pending);
+                }
+                Async::Ready(Some(TtyChunk::StdErr(bytes))) => {
+                    self.stderr_buf.extend(bytes);
+                    split_lines(&mut self.stderr_buf, LogStream::Stderr, &mut self.pending);
+                }
+                Async::Ready(Some(TtyChunk::StdIn(_))) => {}
+                Async::Ready(None) => {
+                    self.done = true;
+                    self.flush_remainder();
+                }
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+        }
+    }
+}
+
+fn split_lines(buffer: &mut Vec<u8>, stream: LogStream, pending: &mut VecDeque<LogLine>) {
+    while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+        let line: Vec<u8> = buffer.drain(..=pos).collect();
+        let message = String::from_utf8_lossy(&line[..line.len() - 1]).into_owned();
+        pending.push_back(LogLine { stream, message });
+    }
+}
+
+/// Options controlling [`Container::exec`].
+pub struct ExecOptions {
+    pub env: Vec<(String, String)>,
+    pub working_dir: Option<String>,
+    pub attach_stdout: bool,
+    pub attach_stderr: bool,
+}
+
+impl Default for ExecOptions {
+    fn default() -> Self {
+        ExecOptions {
+            env: Vec::new(),
+            working_dir: None,
+            attach_stdout: true,
+            attach_stderr: true,
+        }
+    }
+}
+
+/// The combined output of a completed [`Container::exec`] call.
+#[derive(Debug, Default)]
+pub struct ExecOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+fn exec_container<S: AsRef<str>>(
+    client: Client,
+    id: String,
+    cmd: &[S],
+    options: ExecOptions,
+) -> impl Future<Item = ExecOutput, Error = Error> {
+    let cmd: Vec<&str> = cmd.iter().map(AsRef::as_ref).collect();
+    let env: Vec<String> = options
+        .env
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    let env: Vec<&str> = env.iter().map(String::as_str).collect();
+
+    let mut builder = ExecContainerOptions::builder();
+    builder
+        .cmd(cmd)
+        .env(env)
+        .attach_stdout(options.attach_stdout)
+        .attach_stderr(options.attach_stderr);
+
+    if let Some(working_dir) = options.working_dir.as_ref() {
+        builder.working_dir(working_dir);
+    }
+
+    client
+        .containers()
+        .get(&id)
+        .exec(&builder.build())
+        .fold(ExecOutput::default(), |mut output, chunk| {
+            match chunk {
+                TtyChunk::StdOut(bytes) => output.stdout.extend(bytes),
+                TtyChunk::StdErr(bytes) => output.stderr.extend(bytes),
+                TtyChunk::StdIn(_) => {}
+            }
+            Ok(output) as Result<_, shiplift::Error>
+        })
+        .from_err()
+}
+
+/// A bind mount or named volume to attach to a container.
+pub struct Volume {
+    pub host_path: String,
+    pub container_path: String,
+    pub read_only: bool,
+}
+
+/// A readiness gate that [`ContainerBuilder::build`] waits on before
+/// resolving.
+#[derive(Clone, Copy)]
+enum Readiness {
+    /// Resolve as soon as `start` + `inspect` complete (the old behaviour).
+    None,
+    /// Wait until `State.Running` is true.
+    Running,
+    /// Wait until `State.Running` is true and a TCP connect to the given
+    /// mapped host port succeeds.
+    Port(u16),
 }
 
 pub struct ContainerBuilder {
@@ -66,6 +397,13 @@ pub struct ContainerBuilder {
     image_tag: String,
     name: Option<String>,
     ports: Vec<Port>,
+    volumes: Vec<Volume>,
+    env: Vec<(String, String)>,
+    env_file: Vec<(String, String)>,
+    readiness: Readiness,
+    readiness_timeout: Duration,
+    network: Option<String>,
+    create_network: bool,
 
     client: Arc<Docker>,
 
@@ -80,6 +418,13 @@ impl ContainerBuilder {
             image_tag: String::from("latest"),
             name: None,
             ports: Vec::new(),
+            volumes: Vec::new(),
+            env: Vec::new(),
+            env_file: Vec::new(),
+            readiness: Readiness::None,
+            readiness_timeout: Duration::from_secs(30),
+            network: None,
+            create_network: false,
 
             client: Arc::clone(client),
 
@@ -126,19 +471,274 @@ impl ContainerBuilder {
         self
     }
 
-    pub fn build(self) -> impl Future<Item = Container, Error = shiplift::Error> {
+    /// Bind-mount (or attach a named volume at) `host_path` to
+    /// `container_path`.
+    pub fn volume(
+        mut self,
+        host_path: impl Into<String>,
+        container_path: impl Into<String>,
+        read_only: bool,
+    ) -> Self {
+        self.volumes.push(Volume {
+            host_path: host_path.into(),
+            container_path: container_path.into(),
+            read_only,
+        });
+        self
+    }
+
+    /// Shorthand for a writable [`ContainerBuilder::volume`].
+    pub fn mount(self, host_path: impl Into<String>, container_path: impl Into<String>) -> Self {
+        self.volume(host_path, container_path, false)
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Load `KEY=VALUE` pairs from an env file, ignoring blank lines,
+    /// `#` comments, and tolerating an `export ` prefix. Values set via
+    /// explicit [`ContainerBuilder::env`] calls always take precedence
+    /// over ones loaded from a file, regardless of call order.
+    pub fn env_file(mut self, path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        self.env_file.extend(parse_env_file(&contents));
+        Ok(self)
+    }
+
+    fn merged_env(&self) -> Vec<String> {
+        merge_env(&self.env, &self.env_file)
+    }
+
+    /// Wait until the container reports `State.Running` before resolving.
+    pub fn wait_for_running(mut self) -> Self {
+        self.readiness = Readiness::Running;
+        self
+    }
+
+    /// Wait until the container is running *and* a TCP connect to the
+    /// mapped `host_port` succeeds before resolving.
+    pub fn wait_for_port(mut self, host_port: u16) -> Self {
+        self.readiness = Readiness::Port(host_port);
+        self
+    }
+
+    /// Overall deadline for the readiness gate configured by
+    /// [`ContainerBuilder::wait_for_running`] or
+    /// [`ContainerBuilder::wait_for_port`]. Defaults to 30 seconds.
+    pub fn readiness_timeout(mut self, timeout: Duration) -> Self {
+        self.readiness_timeout = timeout;
+        self
+    }
+
+    /// Join a user-defined Docker network instead of the default bridge.
+    /// Containers on the same named network can resolve each other by
+    /// container name/alias.
+    pub fn network(mut self, name: impl Into<String>) -> Self {
+        self.network = Some(name.into());
+        self
+    }
+
+    /// Create the network given to [`ContainerBuilder::network`] first, if
+    /// it doesn't already exist. Has no effect unless
+    /// [`ContainerBuilder::network`] is also set.
+    pub fn create_network_if_missing(mut self) -> Self {
+        self.create_network = true;
+        self
+    }
+
+    pub fn build(self) -> impl Future<Item = Container, Error = Error> {
         let image = self.image();
         let name = self.slugged_name();
         let ports = self.ports;
+        let volumes = self.volumes;
+        let env = self.merged_env();
+        let readiness = self.readiness;
+        let readiness_timeout = self.readiness_timeout;
+        let network = self.network;
+        let create_network = self.create_network;
+        let pull_on_build = self.pull_on_build;
+        let client = self.client;
 
-        pull_image_if(self.client, image, self.pull_on_build)
-            .and_then(|(client, image)| create_container(client, image, name, ports))
-            .and_then(|(client, id)| run_container(client, id))
-            .and_then(|(client, id)| inspect_container(client, id))
+        ensure_network(client, network, create_network)
+            .and_then(move |(client, network)| {
+                pull_image_if(client, image, pull_on_build)
+                    .from_err()
+                    .and_then(move |(client, image)| {
+                        create_container(client, image, name, ports, volumes, env, network)
+                            .from_err()
+                    })
+            })
+            .and_then(|(client, id)| run_container(client, id).from_err())
+            .and_then(move |(client, id)| wait_until_ready(client, id, readiness, readiness_timeout))
+            .and_then(|(client, id)| inspect_container(client, id).from_err())
             .map(|(client, details)| Container { details, client })
     }
 }
 
+fn ensure_network(
+    client: Client,
+    network: Option<String>,
+    create_if_missing: bool,
+) -> impl Future<Item = (Client, Option<String>), Error = Error> {
+    let name = match network {
+        Some(name) if create_if_missing => name,
+        other => return future::Either::A(future::ok((client, other))),
+    };
+
+    let name_for_result = name.clone();
+
+    let fut = client
+        .networks()
+        .list(&Default::default())
+        .from_err()
+        .and_then(move |networks| {
+            if networks.iter().any(|n| n.name == name) {
+                future::Either::A(future::ok(client))
+            } else {
+                // Best-effort: if another build() racing us creates the network
+                // first, Docker's conflict error surfaces here rather than being
+                // silently swallowed.
+                let create = client
+                    .networks()
+                    .create(&NetworkCreateOptions::builder(name.as_ref()).build())
+                    .from_err()
+                    .map(move |_| client);
+                future::Either::B(create)
+            }
+        })
+        .map(move |client| (client, Some(name_for_result)));
+
+    future::Either::B(fut)
+}
+
+type RetryState = (Client, String, u32);
+type LoopState = future::Loop<(Client, String), RetryState>;
+
+fn wait_until_ready(
+    client: Client,
+    id: String,
+    readiness: Readiness,
+    timeout: Duration,
+) -> impl Future<Item = (Client, String), Error = Error> {
+    let deadline = Instant::now() + timeout;
+
+    future::loop_fn((client, id, 0u32), move |(client, id, attempt)| {
+        inspect_container(client, id.clone())
+            .from_err()
+            .and_then(move |(client, details)| {
+                if Instant::now() >= deadline {
+                    let fut: Box<dyn Future<Item = LoopState, Error = Error> + Send> =
+                        Box::new(future::err(Error::Timeout));
+                    return fut;
+                }
+
+                let remaining = deadline - Instant::now();
+
+                Box::new(check_ready(details, readiness, remaining).and_then(
+                    move |ready| {
+                        if ready {
+                            let fut: Box<dyn Future<Item = LoopState, Error = Error> + Send> =
+                                Box::new(future::ok(future::Loop::Break((client, id))));
+                            return fut;
+                        }
+
+                        if Instant::now() >= deadline {
+                            return Box::new(future::err(Error::Timeout));
+                        }
+
+                        let backoff_ms = 50u64.saturating_mul(1 << attempt.min(6));
+                        let delay =
+                            Delay::new(Instant::now() + Duration::from_millis(backoff_ms))
+                                .map_err(|_| Error::Timeout)
+                                .map(move |_| future::Loop::Continue((client, id, attempt + 1)));
+
+                        Box::new(delay)
+                    },
+                ))
+            })
+    })
+}
+
+/// Check whether `details` satisfies `readiness`, bounded by `remaining`
+/// (the time left before the overall [`ContainerBuilder::readiness_timeout`]
+/// deadline). The [`Readiness::Port`] case performs a real, timed-out TCP
+/// connect, so it runs on the blocking thread pool rather than the reactor.
+fn check_ready(
+    details: ContainerDetails,
+    readiness: Readiness,
+    remaining: Duration,
+) -> Box<dyn Future<Item = bool, Error = Error> + Send> {
+    match readiness {
+        Readiness::None => Box::new(future::ok(true)),
+        Readiness::Running => Box::new(future::ok(details.state.running)),
+        Readiness::Port(port) => {
+            if details.state.running {
+                Box::new(port_ready(port, remaining))
+            } else {
+                Box::new(future::ok(false))
+            }
+        }
+    }
+}
+
+/// Attempt a single timed TCP connect to `127.0.0.1:port`, bounded by
+/// `timeout`. Runs on the tokio blocking thread pool so a firewalled
+/// (dropped-SYN) port can't stall the reactor past the caller's deadline.
+fn port_ready(port: u16, timeout: Duration) -> impl Future<Item = bool, Error = Error> {
+    future::poll_fn(move || {
+        blocking(move || {
+            let addr = SocketAddr::from(([127, 0, 0, 1], port));
+            TcpStream::connect_timeout(&addr, timeout).is_ok()
+        })
+        .map_err(|_| Error::Timeout)
+    })
+}
+
+/// Parse the contents of a `KEY=VALUE` env file.
+fn parse_env_file(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+
+            let line = if line.starts_with("export ") {
+                &line["export ".len()..]
+            } else {
+                line
+            };
+
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next()?.trim();
+            let value = parts.next()?.trim();
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Merge explicit `env` pairs with ones loaded from an env file, formatting
+/// each as `KEY=VALUE`. Explicit pairs always win over file-sourced ones for
+/// the same key, regardless of which was added first.
+fn merge_env(explicit: &[(String, String)], from_file: &[(String, String)]) -> Vec<String> {
+    let mut env = Vec::with_capacity(explicit.len() + from_file.len());
+
+    for (key, value) in from_file {
+        if !explicit.iter().any(|(k, _)| k == key) {
+            env.push(format!("{}={}", key, value));
+        }
+    }
+
+    for (key, value) in explicit {
+        env.push(format!("{}={}", key, value));
+    }
+
+    env
+}
+
 fn pull_image_if(
     client: Client,
     image: String,
@@ -174,6 +774,9 @@ fn create_container<S: AsRef<str>>(
     image: String,
     container_name: Option<S>,
     ports: impl IntoIterator<Item = Port>,
+    volumes: impl IntoIterator<Item = Volume>,
+    env: Vec<String>,
+    network: Option<String>,
 ) -> impl Future<Item = (Client, String), Error = shiplift::Error> {
     let mut container_options = ContainerOptions::builder(image.as_ref());
 
@@ -185,6 +788,21 @@ fn create_container<S: AsRef<str>>(
         container_options.expose(port.source, port.protocol.as_ref(), port.host);
     }
 
+    let binds: Vec<String> = volumes
+        .into_iter()
+        .map(|volume| {
+            let mode = if volume.read_only { "ro" } else { "rw" };
+            format!("{}:{}:{}", volume.host_path, volume.container_path, mode)
+        })
+        .collect();
+    container_options.volumes(binds.iter().map(String::as_str).collect());
+
+    container_options.env(env.iter().map(String::as_str).collect());
+
+    if let Some(network) = network.as_ref() {
+        container_options.network_mode(network);
+    }
+
     client
         .containers()
         .create(&container_options.build())
@@ -207,4 +825,423 @@ fn inspect_container(
         .get(&id)
         .inspect()
         .map(|details| (client, details))
-}
\ No newline at end of file
+}
+
+/// Builds a Docker image from a local build-context directory, rather than
+/// pulling one from a registry.
+pub struct ImageBuilder {
+    client: Arc<Docker>,
+    context_dir: PathBuf,
+    dockerfile: String,
+    tag: String,
+    build_args: HashMap<String, String>,
+    ignore: Vec<String>,
+}
+
+impl ImageBuilder {
+    pub fn new(client: &Client, context_dir: impl Into<PathBuf>, tag: impl Into<String>) -> Self {
+        ImageBuilder {
+            client: Arc::clone(client),
+            context_dir: context_dir.into(),
+            dockerfile: String::from("Dockerfile"),
+            tag: tag.into(),
+            build_args: HashMap::new(),
+            ignore: Vec::new(),
+        }
+    }
+
+    /// Path to the Dockerfile, relative to the context directory. Defaults
+    /// to `Dockerfile`.
+    pub fn dockerfile(mut self, path: impl Into<String>) -> Self {
+        self.dockerfile = path.into();
+        self
+    }
+
+    pub fn build_arg(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.build_args.insert(key.into(), value.into());
+        self
+    }
+
+    /// Exclude paths (relative to the context directory) from the build
+    /// context, in addition to whatever a `.dockerignore` in the context
+    /// directory already excludes. Supports `.dockerignore`-style globs
+    /// (`*.log`, `**/tmp`) and `!`-prefixed negation; character classes
+    /// (`[abc]`) are not supported and are matched literally, with a
+    /// warning logged.
+    pub fn ignore(mut self, path: impl Into<String>) -> Self {
+        self.ignore.push(path.into());
+        self
+    }
+
+    pub fn build(self) -> impl Future<Item = String, Error = Error> {
+        build_image(
+            self.client,
+            self.context_dir,
+            self.dockerfile,
+            self.tag,
+            self.build_args,
+            self.ignore,
+        )
+    }
+}
+
+fn build_image(
+    client: Client,
+    context_dir: PathBuf,
+    dockerfile: String,
+    tag: String,
+    build_args: HashMap<String, String>,
+    ignore: Vec<String>,
+) -> impl Future<Item = String, Error = Error> {
+    // `shiplift::Images::build` tars its context directory itself, so the
+    // filtering this crate adds on top of `.dockerignore` has to happen by
+    // staging the surviving files into a directory of their own rather than
+    // by handing shiplift a pre-built tar stream.
+    let staging = match package_context(&context_dir, &ignore) {
+        Ok(dir) => dir,
+        Err(e) => return future::Either::A(future::err(Error::Io(e))),
+    };
+
+    let mut options_builder = BuildOptions::builder(staging.path());
+    options_builder.tag(&tag).dockerfile(&dockerfile);
+
+    for (key, value) in &build_args {
+        options_builder.build_arg(key, value);
+    }
+
+    info!("building image: {}", &tag);
+
+    let fut = client
+        .images()
+        .build(&options_builder.build())
+        .for_each(|output| {
+            debug!("{:?}", output);
+            Ok(())
+        })
+        .from_err()
+        .map(move |_| {
+            // Keep the staging directory alive until the build completes.
+            drop(staging);
+            info!("built image: {}", &tag);
+            tag
+        });
+
+    future::Either::B(fut)
+}
+
+/// Walk `context_dir`, applying `.dockerignore` exclusions (plus any extra
+/// `ignore` patterns — see [`ImageBuilder::ignore`] for the supported
+/// pattern syntax), and stage the surviving files into a fresh temporary
+/// directory for Docker's image-build endpoint to tar.
+fn package_context(context_dir: &Path, ignore: &[String]) -> io::Result<TempDir> {
+    let patterns = load_ignore_patterns(context_dir, ignore);
+    let staging = TempDir::new()?;
+
+    for entry in WalkDir::new(context_dir).into_iter().filter_map(Result::ok) {
+        let path = entry.path();
+        let relative = path.strip_prefix(context_dir).unwrap_or(path);
+
+        if relative.as_os_str().is_empty() || is_ignored(relative, &patterns) {
+            continue;
+        }
+
+        if entry.file_type().is_file() {
+            let dest = staging.path().join(relative);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(path, &dest)?;
+        }
+    }
+
+    Ok(staging)
+}
+
+fn load_ignore_patterns(context_dir: &Path, extra: &[String]) -> Vec<String> {
+    let mut patterns = Vec::new();
+
+    if let Ok(contents) = fs::read_to_string(context_dir.join(".dockerignore")) {
+        for line in contents.lines() {
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                patterns.push(line.to_string());
+            }
+        }
+    }
+
+    patterns.extend(extra.iter().cloned());
+    patterns
+}
+
+/// Decide whether `path` is excluded by `patterns`, applying `.dockerignore`
+/// semantics: later patterns override earlier ones, and a leading `!`
+/// re-includes a path excluded by an earlier pattern.
+fn is_ignored(path: &Path, patterns: &[String]) -> bool {
+    let path = path.to_string_lossy();
+    let mut ignored = false;
+
+    for raw in patterns {
+        let (negate, pattern) = match raw.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, raw.as_str()),
+        };
+        let pattern = pattern.trim_end_matches('/');
+
+        if pattern.contains('[') {
+            warn!(
+                "dockerignore pattern `{}` uses a character class, which is not supported; \
+                 matching it literally",
+                pattern
+            );
+        }
+
+        let matches = path == pattern
+            || path.starts_with(&format!("{}/", pattern))
+            || glob_match(pattern, &path);
+
+        if matches {
+            ignored = !negate;
+        }
+    }
+
+    ignored
+}
+
+/// Match `path` against a `.dockerignore`-style glob: `*` and `?` match
+/// within a single path segment, while a `**` segment matches zero or more
+/// whole segments.
+fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (None, Some(_)) => false,
+        (Some(&"**"), _) => {
+            (0..=path.len()).any(|skip| match_segments(&pattern[1..], &path[skip..]))
+        }
+        (Some(&segment_pattern), Some(&segment)) => {
+            match_segment(segment_pattern, segment) && match_segments(&pattern[1..], &path[1..])
+        }
+        (Some(_), None) => false,
+    }
+}
+
+fn match_segment(pattern: &str, segment: &str) -> bool {
+    fn helper(pattern: &[u8], segment: &[u8]) -> bool {
+        match (pattern.first(), segment.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => {
+                (0..=segment.len()).any(|skip| helper(&pattern[1..], &segment[skip..]))
+            }
+            (Some(b'?'), Some(_)) => helper(&pattern[1..], &segment[1..]),
+            (Some(p), Some(s)) if p == s => helper(&pattern[1..], &segment[1..]),
+            _ => false,
+        }
+    }
+
+    helper(pattern.as_bytes(), segment.as_bytes())
+}
+
+fn copy_into_container(
+    client: Client,
+    id: String,
+    local_path: PathBuf,
+    container_path: String,
+) -> impl Future<Item = (), Error = Error> {
+    let archive = match tar_path(&local_path) {
+        Ok(bytes) => bytes,
+        Err(e) => return future::Either::A(future::err(Error::Io(e))),
+    };
+
+    let fut = client
+        .containers()
+        .get(&id)
+        .put_archive(&container_path, archive)
+        .from_err();
+
+    future::Either::B(fut)
+}
+
+fn copy_from_container(
+    client: Client,
+    id: String,
+    container_path: String,
+) -> impl Future<Item = Vec<u8>, Error = Error> {
+    client
+        .containers()
+        .get(&id)
+        .copy_from(&container_path)
+        .concat2()
+        .from_err()
+        .map(|chunk| chunk.to_vec())
+}
+
+/// Tar up a local file or directory tree, naming the root entry after
+/// `local_path`'s final path component.
+fn tar_path(local_path: &Path) -> io::Result<Vec<u8>> {
+    let mut tar = TarBuilder::new(Vec::new());
+    let name = local_path.file_name().unwrap_or_default();
+
+    if local_path.is_dir() {
+        tar.append_dir_all(name, local_path)?;
+    } else {
+        tar.append_path_with_name(local_path, name)?;
+    }
+
+    tar.into_inner()
+}
+
+fn unpack_tar(archive: &[u8], destination: &Path) -> io::Result<()> {
+    fs::create_dir_all(destination)?;
+    Archive::new(archive).unpack(destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_env_file_skips_blank_lines_and_comments() {
+        let contents = "FOO=bar\n\n# a comment\nBAZ=qux\n";
+        let parsed = parse_env_file(contents);
+
+        assert_eq!(
+            parsed,
+            vec![
+                (String::from("FOO"), String::from("bar")),
+                (String::from("BAZ"), String::from("qux")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_env_file_strips_export_prefix_and_whitespace() {
+        let contents = "  export FOO = bar  \n";
+        let parsed = parse_env_file(contents);
+
+        assert_eq!(parsed, vec![(String::from("FOO"), String::from("bar"))]);
+    }
+
+    #[test]
+    fn merge_env_prefers_explicit_over_file() {
+        let explicit = vec![(String::from("FOO"), String::from("explicit"))];
+        let from_file = vec![
+            (String::from("FOO"), String::from("file")),
+            (String::from("BAR"), String::from("file")),
+        ];
+
+        let merged = merge_env(&explicit, &from_file);
+
+        assert_eq!(merged, vec!["BAR=file", "FOO=explicit"]);
+    }
+
+    #[test]
+    fn merge_env_explicit_wins_regardless_of_order() {
+        // Same keys as above, but FOO is the later of two explicit entries.
+        // Explicit and file pairs reach merge_env as separate slices, so the
+        // order of `env` / `env_file` calls on the builder cannot matter.
+        let explicit = vec![
+            (String::from("BAR"), String::from("explicit")),
+            (String::from("FOO"), String::from("explicit")),
+        ];
+        let from_file = vec![(String::from("FOO"), String::from("file"))];
+
+        let merged = merge_env(&explicit, &from_file);
+
+        assert_eq!(merged, vec!["BAR=explicit", "FOO=explicit"]);
+    }
+
+    #[test]
+    fn split_lines_buffers_partial_lines_across_frames() {
+        let mut splitter = LineSplitter::new(());
+
+        splitter.stdout_buf.extend_from_slice(b"partial");
+        split_lines(&mut splitter.stdout_buf, LogStream::Stdout, &mut splitter.pending);
+        assert!(splitter.pending.is_empty());
+
+        splitter.stdout_buf.extend_from_slice(b" line\nmore");
+        split_lines(&mut splitter.stdout_buf, LogStream::Stdout, &mut splitter.pending);
+
+        let line = splitter.pending.pop_front().expect("one complete line");
+        assert_eq!(line.message, "partial line");
+        assert!(splitter.pending.is_empty());
+        assert_eq!(splitter.stdout_buf, b"more");
+    }
+
+    #[test]
+    fn split_lines_keeps_stdout_and_stderr_buffers_independent() {
+        let mut splitter = LineSplitter::new(());
+
+        splitter.stdout_buf.extend_from_slice(b"out\n");
+        split_lines(&mut splitter.stdout_buf, LogStream::Stdout, &mut splitter.pending);
+
+        splitter.stderr_buf.extend_from_slice(b"err\n");
+        split_lines(&mut splitter.stderr_buf, LogStream::Stderr, &mut splitter.pending);
+
+        let first = splitter.pending.pop_front().expect("stdout line");
+        let second = splitter.pending.pop_front().expect("stderr line");
+        assert_eq!((first.stream, first.message.as_str()), (LogStream::Stdout, "out"));
+        assert_eq!((second.stream, second.message.as_str()), (LogStream::Stderr, "err"));
+    }
+
+    #[test]
+    fn flush_remainder_emits_trailing_partial_lines_on_eof() {
+        let mut splitter = LineSplitter::new(());
+        splitter.stdout_buf.extend_from_slice(b"trailing stdout");
+        splitter.stderr_buf.extend_from_slice(b"trailing stderr");
+
+        splitter.flush_remainder();
+
+        assert!(splitter.stdout_buf.is_empty());
+        assert!(splitter.stderr_buf.is_empty());
+        assert_eq!(splitter.pending.len(), 2);
+        assert_eq!(splitter.pending[0].message, "trailing stdout");
+        assert_eq!(splitter.pending[1].message, "trailing stderr");
+    }
+
+    #[test]
+    fn is_ignored_matches_exact_path() {
+        let patterns = vec![String::from("secrets.env")];
+        assert!(is_ignored(Path::new("secrets.env"), &patterns));
+        assert!(!is_ignored(Path::new("other.env"), &patterns));
+    }
+
+    #[test]
+    fn is_ignored_matches_directory_prefix() {
+        let patterns = vec![String::from("target/")];
+        assert!(is_ignored(Path::new("target/debug/app"), &patterns));
+        assert!(!is_ignored(Path::new("targets/app"), &patterns));
+    }
+
+    #[test]
+    fn is_ignored_matches_star_glob() {
+        let patterns = vec![String::from("*.log")];
+        assert!(is_ignored(Path::new("debug.log"), &patterns));
+        assert!(!is_ignored(Path::new("logs/debug.log"), &patterns));
+    }
+
+    #[test]
+    fn is_ignored_matches_double_star_glob() {
+        let patterns = vec![String::from("**/node_modules")];
+        assert!(is_ignored(Path::new("node_modules"), &patterns));
+        assert!(is_ignored(Path::new("packages/app/node_modules"), &patterns));
+        assert!(!is_ignored(Path::new("node_modules2"), &patterns));
+    }
+
+    #[test]
+    fn is_ignored_honours_negation() {
+        let patterns = vec![String::from("*.log"), String::from("!keep.log")];
+        assert!(is_ignored(Path::new("debug.log"), &patterns));
+        assert!(!is_ignored(Path::new("keep.log"), &patterns));
+    }
+
+    #[test]
+    fn is_ignored_later_pattern_overrides_earlier() {
+        let patterns = vec![String::from("!important.txt"), String::from("*.txt")];
+        assert!(is_ignored(Path::new("important.txt"), &patterns));
+    }
+}